@@ -0,0 +1,31 @@
+/* C interface to the Lox interpreter. Build the shared library with
+ *
+ *     cargo rustc --lib --release --features ffi --crate-type cdylib
+ *
+ * Evaluation writes program output to the host process's stdout and
+ * diagnostics to its stderr. */
+#ifndef LOX_INTERPRETER_H
+#define LOX_INTERPRETER_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct LoxInterpreter LoxInterpreter;
+
+LoxInterpreter *lox_interpreter_new(void);
+void lox_interpreter_free(LoxInterpreter *handle);
+
+/* Returns 0 on success, 65 for scan errors, 70 for runtime or internal
+ * errors, and -1 for a null handle or a source that is not valid UTF-8. */
+int lox_interpreter_eval(LoxInterpreter *handle, const char *source);
+
+/* Message for the last failed eval, or NULL. Owned by the interpreter and
+ * valid until the next eval or free. */
+const char *lox_interpreter_last_error(const LoxInterpreter *handle);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,147 @@
+//! C interface for embedding the interpreter in non-Rust hosts. See `include/lox_interpreter.h`.
+//!
+//! Build it as a shared library with
+//! `cargo rustc --lib --release --features ffi --crate-type cdylib`.
+
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    ptr,
+};
+
+use crate::Interpreter;
+
+/// Returned by `lox_interpreter_eval` when the handle or source is invalid.
+const INVALID_ARGUMENT: c_int = -1;
+
+pub struct LoxInterpreter {
+    interpreter: Interpreter,
+    last_error: Option<CString>,
+}
+
+#[no_mangle]
+pub extern "C" fn lox_interpreter_new() -> *mut LoxInterpreter {
+    Box::into_raw(Box::new(LoxInterpreter {
+        interpreter: Interpreter::new(),
+        last_error: None,
+    }))
+}
+
+/// # Safety
+///
+/// `handle` must be null or a pointer returned by `lox_interpreter_new` that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn lox_interpreter_free(handle: *mut LoxInterpreter) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Runs the NUL-terminated UTF-8 `source`. Returns 0 on success, the CLI exit code for the error
+/// (65 or 70) on failure, or -1 if an argument is invalid.
+///
+/// # Safety
+///
+/// `handle` must be a live pointer from `lox_interpreter_new`, and `source` must be null or point
+/// to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn lox_interpreter_eval(
+    handle: *mut LoxInterpreter,
+    source: *const c_char,
+) -> c_int {
+    let Some(handle) = handle.as_mut() else {
+        return INVALID_ARGUMENT;
+    };
+    handle.last_error = None;
+    if source.is_null() {
+        return INVALID_ARGUMENT;
+    }
+
+    let source = match CStr::from_ptr(source).to_str() {
+        Ok(source) => source,
+        Err(err) => {
+            handle.last_error = CString::new(err.to_string()).ok();
+            return INVALID_ARGUMENT;
+        }
+    };
+    match handle.interpreter.run(source) {
+        Ok(()) => 0,
+        Err(err) => {
+            handle.last_error = CString::new(err.to_string()).ok();
+            err.exit_code()
+        }
+    }
+}
+
+/// Returns the message of the error from the last `lox_interpreter_eval`, or null if it succeeded.
+/// The string is owned by the interpreter and valid until the next eval or free.
+///
+/// # Safety
+///
+/// `handle` must be null or a live pointer from `lox_interpreter_new`.
+#[no_mangle]
+pub unsafe extern "C" fn lox_interpreter_last_error(
+    handle: *const LoxInterpreter,
+) -> *const c_char {
+    handle
+        .as_ref()
+        .and_then(|handle| handle.last_error.as_ref())
+        .map_or(ptr::null(), |err| err.as_ptr())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    /// A handle whose interpreter discards program output and diagnostics.
+    fn quiet_handle() -> *mut LoxInterpreter {
+        let handle = lox_interpreter_new();
+        unsafe {
+            (*handle).interpreter = Interpreter::new()
+                .with_output(io::sink())
+                .with_error_output(io::sink());
+        }
+        handle
+    }
+
+    #[test]
+    fn rejects_null_arguments() {
+        let handle = quiet_handle();
+        unsafe {
+            assert_eq!(
+                lox_interpreter_eval(ptr::null_mut(), c"var a;".as_ptr()),
+                -1
+            );
+            assert_eq!(lox_interpreter_eval(handle, ptr::null()), -1);
+            assert!(lox_interpreter_last_error(ptr::null()).is_null());
+            lox_interpreter_free(handle);
+            lox_interpreter_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_utf8() {
+        let handle = quiet_handle();
+        unsafe {
+            assert_eq!(lox_interpreter_eval(handle, c"var \xff;".as_ptr()), -1);
+            assert!(!lox_interpreter_last_error(handle).is_null());
+            lox_interpreter_free(handle);
+        }
+    }
+
+    #[test]
+    fn reports_errors_until_the_next_success() {
+        let handle = quiet_handle();
+        unsafe {
+            assert_eq!(lox_interpreter_eval(handle, c"var a = @;".as_ptr()), 65);
+            let err = lox_interpreter_last_error(handle);
+            assert!(!err.is_null());
+            assert!(!CStr::from_ptr(err).to_bytes().is_empty());
+
+            assert_eq!(lox_interpreter_eval(handle, c"var a = 1;".as_ptr()), 0);
+            assert!(lox_interpreter_last_error(handle).is_null());
+            lox_interpreter_free(handle);
+        }
+    }
+}
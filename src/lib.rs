@@ -0,0 +1,14 @@
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod lox;
+
+pub use lox::{
+    error::{ColorChoice, Diagnostic, ErrorFormat, Label, LoxError, Severity},
+    input::InputSource,
+    interpreter::{Interpreter, RunReport},
+    lint::{Level, Lint, LintLevels, LINTS, NON_ASCII_IDENTS},
+    messages::{set_lang, usage, Lang},
+    repl::Repl,
+    scanner::{Literal, Parser, Token, TokenType},
+    Result,
+};
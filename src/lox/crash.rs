@@ -0,0 +1,211 @@
+use std::{
+    backtrace::Backtrace,
+    cell::{Cell, RefCell},
+    env, fmt,
+    fs::OpenOptions,
+    io::Write,
+    panic::{self, AssertUnwindSafe, PanicHookInfo},
+    path::PathBuf,
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Once,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::messages::{message, Key};
+
+type Hook = Box<dyn Fn(&PanicHookInfo<'_>) + Sync + Send + 'static>;
+
+static INSTALL_HOOK: Once = Once::new();
+static REPORTS: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static CONTEXT: Cell<Context> = const { Cell::new(Context::new()) };
+    /// Whether this thread is inside `guard`; panics anywhere else belong to the host.
+    static GUARDED: Cell<bool> = const { Cell::new(false) };
+    /// What to tell the user about the last guarded panic on this thread.
+    static OUTCOME: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Idle,
+    Scanning,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Idle => "idle",
+            Self::Scanning => "scanning",
+        })
+    }
+}
+
+/// What the interpreter was doing when it panicked, recorded as it goes so the report
+/// can be written after the stack has unwound.
+#[derive(Debug, Clone, Copy)]
+struct Context {
+    phase: Phase,
+    source_len: usize,
+    tokens: usize,
+    line: usize,
+    col: usize,
+}
+
+impl Context {
+    const fn new() -> Self {
+        Context {
+            phase: Phase::Idle,
+            source_len: 0,
+            tokens: 0,
+            line: 0,
+            col: 0,
+        }
+    }
+}
+
+pub fn enter_phase(phase: Phase, source_len: usize) {
+    CONTEXT.set(Context {
+        phase,
+        source_len,
+        ..Context::new()
+    });
+}
+
+pub fn set_position(line: usize, col: usize, tokens: usize) {
+    CONTEXT.set(Context {
+        line,
+        col,
+        tokens,
+        ..CONTEXT.get()
+    });
+}
+
+/// Runs `f`, writing a crash report file if it panics instead of printing the raw Rust panic.
+/// Returns the message to show the user in place of the panic.
+///
+/// Panics outside `guard`, including on the host's own threads, still go to the panic hook that
+/// was installed before the first call.
+pub fn guard<R>(f: impl FnOnce() -> R) -> Result<R, String> {
+    INSTALL_HOOK.call_once(|| panic::set_hook(chain(panic::take_hook())));
+    let was_guarded = GUARDED.replace(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    GUARDED.set(was_guarded);
+    CONTEXT.set(Context::new());
+    result.map_err(|_| OUTCOME.take().unwrap_or_default())
+}
+
+/// Wraps `previous` so guarded panics are reported and every other panic is passed through.
+fn chain(previous: Hook) -> Hook {
+    Box::new(move |info| {
+        if GUARDED.get() {
+            OUTCOME.set(Some(report(&info.to_string())));
+        } else {
+            previous(info);
+        }
+    })
+}
+
+fn report(panic_msg: &str) -> String {
+    let context = CONTEXT.get();
+    let report = format!(
+        "interpreter-rs {} internal error\n\n\
+         {}\n\n\
+         phase: {}\n\
+         position: line {}, col {}\n\
+         tokens scanned: {}\n\
+         source length: {} bytes\n\n\
+         backtrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        panic_msg,
+        context.phase,
+        context.line,
+        context.col,
+        context.tokens,
+        context.source_len,
+        Backtrace::force_capture(),
+    );
+
+    let path = report_path();
+    // `create_new` refuses to follow a symlink planted at the path or to clobber another report.
+    let written = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(report.as_bytes()));
+    match written {
+        Ok(()) => message(Key::InternalError, &[&path.display()]),
+        Err(err) => message(Key::CrashReportFailed, &[&err]),
+    }
+}
+
+fn report_path() -> PathBuf {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let n = REPORTS.fetch_add(1, Ordering::Relaxed);
+    env::temp_dir().join(format!("lox-crash-{}-{}-{}.txt", secs, process::id(), n))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, thread};
+
+    use super::*;
+
+    thread_local! {
+        /// Set by the test hook when it sees a panic on this thread.
+        static SEEN: Cell<bool> = const { Cell::new(false) };
+    }
+
+    /// The report file named in a guarded panic's message.
+    fn report_file(err: &str) -> PathBuf {
+        let dir = env::temp_dir().display().to_string();
+        let start = err.find(&dir).expect("message names the report");
+        let end = err.rfind(".txt").expect("message names the report") + ".txt".len();
+        PathBuf::from(&err[start..end])
+    }
+
+    #[test]
+    fn guarded_panic_writes_a_report() {
+        let err = guard(|| panic!("boom")).unwrap_err();
+        let path = report_file(&err);
+
+        let report = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert!(report.contains("boom"));
+        assert!(report.contains("phase: idle"));
+    }
+
+    #[test]
+    fn unguarded_panic_reaches_previous_hook() {
+        // Chain onto whatever hook is installed now, recording panics from test threads
+        // instead of printing them, and leave it installed for the rest of the run.
+        let original = panic::take_hook();
+        panic::set_hook(chain(Box::new(move |info| {
+            if thread::current().name() == Some("unguarded") {
+                SEEN.set(true);
+            } else {
+                original(info);
+            }
+        })));
+
+        let seen = thread::Builder::new()
+            .name("unguarded".to_string())
+            .spawn(|| {
+                let _ = panic::catch_unwind(|| panic!("host panic"));
+                let outside = SEEN.take();
+                let err = guard(|| panic!("boom")).unwrap_err();
+                let _ = fs::remove_file(report_file(&err));
+                (outside, SEEN.get())
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(seen, (true, false));
+    }
+}
@@ -1,45 +1,509 @@
-use std::fmt::Display;
+use std::{
+    error::Error,
+    fmt,
+    fmt::Display,
+    io::{self, IsTerminal},
+    iter,
+    str::FromStr,
+};
 
-pub enum LoxError<'a> {
-    Parser(ParseError<'a>),
+use itertools::Itertools;
+
+use super::messages::{lang, message, message_in, Key, Lang};
+
+const BOLD_RED: &str = "1;31";
+const BOLD_YELLOW: &str = "1;33";
+const BLUE: &str = "34";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err(message(Key::UnknownColor, &[&s])),
+        }
+    }
+}
+
+impl ColorChoice {
+    /// Whether to use ANSI colors, with `Auto` using them when stderr is a terminal.
+    pub fn enabled(self) -> bool {
+        match self {
+            Self::Auto => io::stderr().is_terminal(),
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+/// How the interpreter prints diagnostics: for people, or as one JSON object per line for tools.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(ErrorFormat::Human),
+            "json" => Ok(ErrorFormat::Json),
+            _ => Err(message(Key::UnknownErrorFormat, &[&s])),
+        }
+    }
+}
+
+/// Columns `c` takes up in diagnostics, which expand tabs to four spaces.
+pub(crate) fn columns(c: char) -> usize {
+    if c == '\t' {
+        4
+    } else {
+        1
+    }
+}
+
+/// Converts the display column `col` on `line_text` to a 1-based character column, which is what
+/// editors expect. Columns past the end of the line stay past it.
+fn char_col(line_text: &str, col: usize) -> usize {
+    let mut display = 1;
+    let mut chars = 0;
+    for c in line_text.chars() {
+        if display >= col {
+            return chars + 1;
+        }
+        display += columns(c);
+        chars += 1;
+    }
+    chars + 1 + col.saturating_sub(display)
+}
+
+fn paint(color: bool, style: &str, text: impl Display) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", style, text)
+    } else {
+        text.to_string()
+    }
+}
+
+#[derive(Debug)]
+pub enum LoxError {
+    Parser(Vec<Diagnostic>),
     Runtime,
+    /// A bug in the interpreter itself; the details are in the crash report.
+    Internal,
+    /// The script file could not be read.
+    Io {
+        path: String,
+        err: io::Error,
+    },
 }
 
-impl<'a> Display for LoxError<'a> {
+impl LoxError {
+    /// Process exit status for this error, following the sysexits codes used by the book's jlox.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Parser(_) => 65,
+            Self::Runtime | Self::Internal => 70,
+            Self::Io { .. } => 66,
+        }
+    }
+
+    /// Renders the error as JSON lines, one per diagnostic. Errors without a source location
+    /// use their name as the code and `null` for the position; `msg` replaces their text.
+    pub fn to_json(&self, msg: Option<&str>, file: Option<&str>) -> String {
+        let code = match self {
+            Self::Parser(errors) => return errors.iter().map(Diagnostic::to_json).join("\n"),
+            Self::Runtime => "runtime",
+            Self::Internal => "internal",
+            Self::Io { .. } => "io",
+        };
+        format!(
+            "{{\"code\":{},\"severity\":\"{}\",\"message\":{},\"file\":{},\"line\":null,\"col\":null,\"span\":null,\"labels\":[],\"notes\":[]}}",
+            json_string(code),
+            Severity::Error.as_str(),
+            json_string(&msg.map_or_else(|| self.to_string(), str::to_string)),
+            file.map_or(String::from("null"), json_string),
+        )
+    }
+}
+
+impl Display for LoxError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Parser(err) => write!(f, "{}", err),
-            Self::Runtime => write!(f, "Runtime error"),
+            Self::Parser(errors) => {
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", err)?;
+                }
+                Ok(())
+            }
+            Self::Runtime => write!(f, "{}", message(Key::RuntimeError, &[])),
+            Self::Internal => write!(f, "{}", message(Key::InternalErrorSummary, &[])),
+            Self::Io { path, err } => write!(f, "{}", message(Key::FileError, &[path, err])),
         }
     }
 }
 
-pub struct ParseError<'a> {
-    msg: String,
-    code: &'a str,
-    col: usize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        }
+    }
+}
+
+/// A span of source text called out by a diagnostic, with what it has to do with the problem.
+#[derive(Debug, Clone)]
+pub struct Label {
     line: usize,
+    /// 1-based column where the span starts, at least one.
+    col: usize,
+    /// Columns to underline, at least one.
+    width: usize,
+    /// The whole source line the span is on.
+    line_text: String,
+    text: String,
 }
 
-impl<'a> ParseError<'a> {
-    pub fn new(msg: String, code: &'a str, col: usize, line: usize) -> Self {
-        ParseError {
-            msg,
-            code,
-            col,
+impl Label {
+    pub fn new(line_text: &str, col: usize, width: usize, line: usize, text: String) -> Self {
+        Label {
             line,
+            col: col.max(1),
+            width: width.max(1),
+            line_text: line_text.to_string(),
+            text,
         }
     }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The span as 1-based character columns, with the end exclusive.
+    fn char_span(&self) -> (usize, usize) {
+        (
+            char_col(&self.line_text, self.col),
+            char_col(&self.line_text, self.col + self.width),
+        )
+    }
+
+    fn to_json(&self) -> String {
+        let (start, end) = self.char_span();
+        format!(
+            "{{\"line\":{},\"start\":{},\"end\":{},\"message\":{}}}",
+            self.line,
+            start,
+            end,
+            json_string(&self.text),
+        )
+    }
 }
 
-impl<'a> Display for ParseError<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        writeln!(f, "Error: {}", &self.msg)?;
-        writeln!(f, "    {} | {}", self.line, self.code)?;
+/// A message about a location in the source, reported by the scanner.
+///
+/// It always has a primary label at the offending text, and may add secondary labels at related
+/// places and notes or help at the end.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    severity: Severity,
+    /// Stable identifier: `E`-numbers for hard errors, the lint name for lints.
+    code: &'static str,
+    msg: String,
+    /// The script the source came from, if it came from a file.
+    file: Option<String>,
+    primary: Label,
+    secondary: Vec<Label>,
+    notes: Vec<(Key, String)>,
+    /// The language the diagnostic was reported in, so it renders the same way later.
+    lang: Lang,
+}
+
+impl Diagnostic {
+    pub fn new(
+        severity: Severity,
+        code: &'static str,
+        msg: String,
+        line_text: &str,
+        col: usize,
+        width: usize,
+        line: usize,
+    ) -> Self {
+        Diagnostic {
+            severity,
+            code,
+            msg,
+            file: None,
+            primary: Label::new(line_text, col, width, line, message(Key::Here, &[])),
+            secondary: Vec::new(),
+            notes: Vec::new(),
+            lang: lang(),
+        }
+    }
+
+    /// Replaces the primary label's default `Here` text.
+    pub fn with_label(mut self, text: String) -> Self {
+        self.primary.text = text;
+        self
+    }
+
+    /// Points out another place in the source that helps explain the problem.
+    pub fn with_secondary(mut self, label: Label) -> Self {
+        self.secondary.push(label);
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.notes.push((Key::Note, note));
+        self
+    }
+
+    /// Suggests how to fix the problem.
+    pub fn with_help(mut self, help: String) -> Self {
+        self.notes.push((Key::Help, help));
+        self
+    }
+
+    pub fn with_file(mut self, file: Option<String>) -> Self {
+        self.file = file;
+        self
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    pub fn code(&self) -> &'static str {
+        self.code
+    }
+
+    pub fn msg(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    pub fn line(&self) -> usize {
+        self.primary.line
+    }
+
+    pub fn col(&self) -> usize {
+        self.primary.col
+    }
+
+    pub fn width(&self) -> usize {
+        self.primary.width
+    }
+
+    pub fn secondary(&self) -> &[Label] {
+        &self.secondary
+    }
+
+    /// Renders the diagnostic as a single-line JSON object. Columns count characters, so a tab is
+    /// one column here rather than the four `Display` and `col()` give it. Spans cover columns
+    /// `start` up to but not including `end`.
+    pub fn to_json(&self) -> String {
+        let (start, end) = self.primary.char_span();
+        let labels = self.secondary.iter().map(Label::to_json).join(",");
+        let notes = self
+            .notes
+            .iter()
+            .map(|(kind, note)| {
+                let kind = match kind {
+                    Key::Help => "help",
+                    _ => "note",
+                };
+                format!(
+                    "{{\"kind\":\"{}\",\"message\":{}}}",
+                    kind,
+                    json_string(note)
+                )
+            })
+            .join(",");
+        format!(
+            "{{\"code\":{},\"severity\":\"{}\",\"message\":{},\"file\":{},\"line\":{},\"col\":{},\"span\":{{\"start\":{},\"end\":{}}},\"labels\":[{}],\"notes\":[{}]}}",
+            json_string(self.code),
+            self.severity.as_str(),
+            json_string(&self.msg),
+            self.file.as_deref().map_or(String::from("null"), json_string),
+            self.line(),
+            start,
+            start,
+            end,
+            labels,
+            notes,
+        )
+    }
+}
+
+/// Quotes `s` as a JSON string literal, escaping quotes, backslashes and control characters.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic as `Display` does, optionally with ANSI colors for a terminal.
+    pub fn render(&self, color: bool) -> String {
+        Rendered(self, color).to_string()
+    }
+
+    /// Lays the diagnostic out like rustc: a header, the location, each labelled source line in
+    /// a numbered gutter with its spans underlined, and then any notes.
+    fn write(&self, f: &mut fmt::Formatter<'_>, color: bool) -> fmt::Result {
+        let (header, style) = match self.severity {
+            Severity::Error => (Key::ErrorHeader, BOLD_RED),
+            Severity::Warning => (Key::WarningHeader, BOLD_YELLOW),
+        };
+        let labels = iter::once((&self.primary, style, '^'))
+            .chain(self.secondary.iter().map(|label| (label, BLUE, '-')))
+            .sorted_by_key(|(label, ..)| (label.line, label.col))
+            .collect::<Vec<_>>();
+        let gutter = " ".repeat(
+            labels
+                .iter()
+                .map(|(label, ..)| label.line.to_string().len())
+                .max()
+                .unwrap_or(1),
+        );
+
         write!(
             f,
-            "{}^--- Here",
-            &" ".repeat(self.col + 6 + self.line.to_string().len())
-        )
+            "{}",
+            paint(
+                color,
+                style,
+                message_in(self.lang, header, &[&self.code, &self.msg])
+            )
+        )?;
+        write!(
+            f,
+            "\n{}{} {}{}:{}",
+            gutter,
+            paint(color, BLUE, "-->"),
+            self.file
+                .as_deref()
+                .map_or(String::new(), |file| format!("{}:", file)),
+            self.line(),
+            self.col()
+        )?;
+        write!(f, "\n{} {}", gutter, paint(color, BLUE, "|"))?;
+
+        let mut last_line = None;
+        for (label, style, mark) in labels {
+            if last_line != Some(label.line) {
+                if last_line.is_some_and(|last| label.line > last + 1) {
+                    write!(f, "\n{}", paint(color, BLUE, "..."))?;
+                }
+                // Tabs are expanded to the four columns the scanner counts them as, so the
+                // underline lines up.
+                write!(
+                    f,
+                    "\n{} {}",
+                    paint(
+                        color,
+                        BLUE,
+                        format!("{:>width$} |", label.line, width = gutter.len())
+                    ),
+                    label.line_text.replace('\t', &" ".repeat(columns('\t')))
+                )?;
+                last_line = Some(label.line);
+            }
+            write!(
+                f,
+                "\n{} {} {}{}",
+                gutter,
+                paint(color, BLUE, "|"),
+                " ".repeat(label.col.saturating_sub(1)),
+                paint(
+                    color,
+                    style,
+                    format!("{} {}", mark.to_string().repeat(label.width), label.text)
+                )
+            )?;
+        }
+
+        for (kind, note) in &self.notes {
+            write!(
+                f,
+                "\n{} {} {}",
+                gutter,
+                paint(color, BLUE, "="),
+                message_in(self.lang, *kind, &[note])
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, false)
+    }
+}
+
+struct Rendered<'a>(&'a Diagnostic, bool);
+
+impl Display for Rendered<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.write(f, self.1)
     }
 }
+
+impl Error for Diagnostic {}
+
+impl Error for LoxError {}
+
+// Hosts need to move errors across threads and box them as `dyn Error + Send + Sync`.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync + 'static>() {}
+    assert_send_sync::<LoxError>();
+};
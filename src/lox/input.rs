@@ -0,0 +1,32 @@
+use std::io::{self, BufRead, IsTerminal};
+
+/// Where the interpreter reads interactive input from, so the REPL can be driven from an
+/// in-memory buffer instead of stdin.
+pub trait InputSource {
+    /// Returns the next line without its line terminator, or `None` once input is exhausted.
+    fn next_line(&mut self) -> Option<String>;
+
+    /// Whether a person is typing the input, in which case the REPL shows prompts.
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+impl<R: BufRead> InputSource for R {
+    fn next_line(&mut self) -> Option<String> {
+        self.lines().next().and_then(|line| line.ok())
+    }
+}
+
+/// Reads from the process's stdin, locking it only for the duration of each line.
+pub struct Stdin;
+
+impl InputSource for Stdin {
+    fn next_line(&mut self) -> Option<String> {
+        io::stdin().lock().next_line()
+    }
+
+    fn is_interactive(&self) -> bool {
+        io::stdin().is_terminal()
+    }
+}
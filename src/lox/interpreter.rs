@@ -1,50 +1,370 @@
 use std::{
+    fmt::Display,
     fs,
-    io::{self, BufRead, Write},
+    io::{self, Write},
     path::Path,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
 };
 
-use super::scanner::Parser;
+use super::{
+    crash::{self, Phase},
+    error::{ColorChoice, Diagnostic, ErrorFormat, LoxError},
+    input::{self, InputSource},
+    lint::LintLevels,
+    messages::{self, Lang},
+    scanner::Parser,
+    Result,
+};
+
+#[derive(Clone)]
+pub struct Interpreter {
+    dump_tokens: bool,
+    check: bool,
+    time: bool,
+    script_args: Vec<String>,
+    lints: LintLevels,
+    error_format: ErrorFormat,
+    color: ColorChoice,
+    lang: Option<Lang>,
+    /// The script being run, named in diagnostics.
+    file: Option<String>,
+    input: Arc<Mutex<dyn InputSource + Send>>,
+    output: Arc<Mutex<dyn Write + Send>>,
+    /// Where diagnostics and `--time` output go; stderr when `None`.
+    errors: Option<Arc<Mutex<dyn Write + Send>>>,
+}
 
-pub struct Interpreter {}
+/// Everything a single run produced, for hosts that want it as data rather than on stdio.
+#[derive(Debug)]
+pub struct RunReport {
+    /// Everything the program wrote to its output.
+    pub output: String,
+    /// Lint warnings, reported whether or not the run succeeded.
+    pub warnings: Vec<Diagnostic>,
+    /// Why the run failed, if it did.
+    pub error: Option<LoxError>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self {
+            dump_tokens: false,
+            check: false,
+            time: false,
+            script_args: Vec::new(),
+            lints: LintLevels::default(),
+            error_format: ErrorFormat::default(),
+            color: ColorChoice::Auto,
+            lang: None,
+            file: None,
+            input: Arc::new(Mutex::new(input::Stdin)),
+            output: Arc::new(Mutex::new(io::stdout())),
+            errors: None,
+        }
+    }
+}
 
 impl Interpreter {
-    pub fn run(source: &str) {
-        let mut parser = Parser::new(source);
-        let tokens = parser.scan_tokens();
-        match tokens {
-            Ok(tokens) => {
-                for t in tokens.iter() {
-                    println!("{:?}", t);
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads interactive input from `input` instead of stdin.
+    pub fn with_input(mut self, input: impl InputSource + Send + 'static) -> Self {
+        self.input = Arc::new(Mutex::new(input));
+        self
+    }
+
+    /// Writes program output to `output` instead of stdout.
+    pub fn with_output(mut self, output: impl Write + Send + 'static) -> Self {
+        self.output = Arc::new(Mutex::new(output));
+        self
+    }
+
+    /// Writes diagnostics and timings to `errors` instead of stderr, so an embedded console can
+    /// show them.
+    pub fn with_error_output(mut self, errors: impl Write + Send + 'static) -> Self {
+        self.errors = Some(Arc::new(Mutex::new(errors)));
+        self
+    }
+
+    /// Stops after scanning and prints one token per line as tab-separated
+    /// `line:col`, type, lexeme and literal fields. Lexemes and string literals
+    /// are printed as JSON string literals, so tabs and newlines are escaped.
+    pub fn with_dump_tokens(mut self, dump_tokens: bool) -> Self {
+        self.dump_tokens = dump_tokens;
+        self
+    }
+
+    /// Stops after the static phases, reporting their diagnostics without running the program.
+    pub fn with_check(mut self, check: bool) -> Self {
+        self.check = check;
+        self
+    }
+
+    /// Prints the wall-clock time spent in each phase to the error output after every run.
+    pub fn with_time(mut self, time: bool) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Sets the arguments passed to the script after `--` on the command line.
+    pub fn with_script_args(mut self, script_args: Vec<String>) -> Self {
+        self.script_args = script_args;
+        self
+    }
+
+    /// Sets which lints are allowed, reported as warnings or denied as errors.
+    pub fn with_lints(mut self, lints: LintLevels) -> Self {
+        self.lints = lints;
+        self
+    }
+
+    /// Prints diagnostics as human-readable text or as JSON lines.
+    pub fn with_error_format(mut self, error_format: ErrorFormat) -> Self {
+        self.error_format = error_format;
+        self
+    }
+
+    /// Sets whether diagnostics printed by `run` use ANSI colors. Errors returned to the caller
+    /// are always plain, and `Auto` never colors a writer set with `with_error_output`.
+    pub fn with_color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Reports diagnostics in `lang` rather than the process-wide default from `set_lang`, so
+    /// interpreters for different users can use different languages.
+    pub fn with_lang(mut self, lang: Lang) -> Self {
+        self.lang = Some(lang);
+        self
+    }
+
+    pub(crate) fn lang(&self) -> Option<Lang> {
+        self.lang
+    }
+
+    pub fn script_args(&self) -> &[String] {
+        &self.script_args
+    }
+
+    /// Reads the next line of interactive input, or `None` once it is exhausted.
+    pub fn read_line(&self) -> Option<String> {
+        lock(&self.input).next_line()
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        lock(&self.input).is_interactive()
+    }
+
+    /// Runs `source`, reporting any errors on the error output before returning them.
+    pub fn run(&mut self, source: &str) -> Result<()> {
+        self.execute(source, true).1
+    }
+
+    /// Runs `source`, collecting its output and any error instead of printing them.
+    pub fn run_capture(&mut self, source: &str) -> RunReport {
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let output = std::mem::replace(&mut self.output, buffer.clone());
+        let (warnings, result) = self.execute(source, false);
+        self.output = output;
+
+        let output = String::from_utf8_lossy(&lock(&buffer)).into_owned();
+        RunReport {
+            output,
+            warnings,
+            error: result.err(),
+        }
+    }
+
+    /// Runs `source`, returning its lint warnings alongside the result.
+    fn execute(&mut self, source: &str, report_errors: bool) -> (Vec<Diagnostic>, Result<()>) {
+        messages::with_lang(self.lang, || self.execute_in_lang(source, report_errors))
+    }
+
+    fn execute_in_lang(
+        &mut self,
+        source: &str,
+        report_errors: bool,
+    ) -> (Vec<Diagnostic>, Result<()>) {
+        let started = Instant::now();
+        let mut timings = Vec::new();
+        let mut warnings = Vec::new();
+        let result = crash::guard(|| {
+            crash::enter_phase(Phase::Scanning, source.len());
+            let mut parser = Parser::new(source).with_lints(self.lints.clone());
+            let scanned = parser.scan_tokens();
+            timings.push((Phase::Scanning, started.elapsed()));
+            let result = match scanned {
+                Ok(_) if self.check => Ok(()),
+                Ok(tokens) if self.dump_tokens => {
+                    let mut output = lock(&self.output);
+                    for t in tokens.iter() {
+                        let _ = writeln!(output, "{}", t);
+                    }
+                    Ok(())
+                }
+                Ok(tokens) => {
+                    let mut output = lock(&self.output);
+                    for t in tokens.iter() {
+                        let _ = writeln!(output, "{:?}", t);
+                    }
+                    Ok(())
+                }
+                Err(errors) => Err(LoxError::Parser(self.in_file(errors))),
+            };
+            warnings = self.in_file(parser.warnings());
+            result
+        });
+
+        if report_errors {
+            warnings.iter().for_each(|warning| self.report(warning));
+        }
+        let result = match result {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(err)) => {
+                if report_errors {
+                    match &err {
+                        LoxError::Parser(errors) => errors.iter().for_each(|err| self.report(err)),
+                        err => self.report_failure(err, None),
+                    }
                 }
+                Err(err)
+            }
+            // The crash message already names the report file, so it stands in for the error.
+            Err(crash) => {
+                if report_errors {
+                    self.report_failure(&LoxError::Internal, Some(&crash));
+                }
+                Err(LoxError::Internal)
+            }
+        };
+
+        if self.time {
+            let phases = timings
+                .into_iter()
+                .map(|(phase, elapsed)| (phase.to_string(), elapsed));
+            for (phase, elapsed) in phases.chain([(String::from("total"), started.elapsed())]) {
+                self.report_time(&phase, elapsed);
             }
-            Err(_) => {}
         }
+        (warnings, result)
     }
 
-    pub fn run_file<T>(file_path: T)
-    where
-        T: AsRef<Path>,
-    {
-        let source = fs::read_to_string(file_path).expect("Should have been able to read the file");
-        Self::run(&source);
+    /// Names the script being run in each of `diagnostics`.
+    fn in_file(&self, diagnostics: &[Diagnostic]) -> Vec<Diagnostic> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.clone().with_file(self.file.clone()))
+            .collect()
     }
 
-    pub fn run_prompt() {
-        let stdin = io::stdin();
-        println!("=== Welcome to the Lox REPL ===");
-        loop {
-            print!("  > ");
-            let _ = io::stdout().flush();
-            if let Some(str_result) = stdin.lock().lines().next() {
-                if let Ok(input) = str_result {
-                    Self::run(&input);
-                } else {
-                    break;
-                }
-            } else {
-                break;
+    fn report(&self, diagnostic: &Diagnostic) {
+        match self.error_format {
+            ErrorFormat::Human => {
+                let color = match self.color {
+                    ColorChoice::Auto if self.errors.is_some() => false,
+                    color => color.enabled(),
+                };
+                self.eprint(diagnostic.render(color))
             }
+            ErrorFormat::Json => self.eprint(diagnostic.to_json()),
+        }
+    }
+
+    /// Reports an error that has no diagnostics of its own, with `msg` in place of its text.
+    fn report_failure(&self, err: &LoxError, msg: Option<&str>) {
+        match self.error_format {
+            ErrorFormat::Human => match msg {
+                Some(msg) => self.eprint(msg),
+                None => self.eprint(err),
+            },
+            ErrorFormat::Json => self.eprint(err.to_json(msg, self.file.as_deref())),
+        }
+    }
+
+    /// Reports how long `phase` took, as a JSON object when diagnostics are JSON so the stream
+    /// stays one object per line.
+    fn report_time(&self, phase: &str, elapsed: Duration) {
+        match self.error_format {
+            ErrorFormat::Human => self.eprint(format_args!("{:>10}  {:?}", phase, elapsed)),
+            ErrorFormat::Json => self.eprint(format_args!(
+                "{{\"phase\":\"{}\",\"seconds\":{}}}",
+                phase,
+                elapsed.as_secs_f64()
+            )),
+        }
+    }
+
+    /// Writes a line to the error output.
+    fn eprint(&self, text: impl Display) {
+        match &self.errors {
+            Some(errors) => {
+                let _ = writeln!(lock(errors), "{}", text);
+            }
+            None => eprintln!("{}", text),
+        }
+    }
+
+    /// Runs the script at `file_path`, reporting a file that cannot be read like any other error.
+    pub fn run_file(&mut self, file_path: impl AsRef<Path>) -> Result<()> {
+        let path = file_path.as_ref().display().to_string();
+        self.file = Some(path.clone());
+        match fs::read_to_string(file_path) {
+            Ok(source) => self.run(&source),
+            Err(err) => {
+                let err = LoxError::Io { path, err };
+                messages::with_lang(self.lang, || self.report_failure(&err, None));
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Locks a shared input or output. A panic while one was held is already reported by the crash
+/// handler, so a poisoned lock is still safe to use.
+fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+// Hosts move interpreters into worker threads.
+const _: () = {
+    const fn assert_send<T: Send>() {}
+    assert_send::<Interpreter>();
+};
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn runs_scripts_concurrently() {
+        let handles = (0..8)
+            .map(|i| {
+                let mut interpreter = Interpreter::new()
+                    .with_output(Vec::new())
+                    .with_dump_tokens(true);
+                thread::spawn(move || {
+                    let source = format!("var v{} = {};", i, i).repeat(100);
+                    (i, interpreter.run_capture(&source))
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            let (i, report) = handle.join().unwrap();
+            assert!(report.error.is_none());
+            let idents = report
+                .output
+                .lines()
+                .filter(|line| line.contains("\tIdent\t"))
+                .collect::<Vec<_>>();
+            assert_eq!(idents.len(), 100);
+            let name = format!("\"v{}\"", i);
+            assert!(idents.iter().all(|line| line.contains(&name)));
         }
     }
 }
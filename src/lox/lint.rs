@@ -0,0 +1,70 @@
+use super::{
+    messages::{message, Key},
+    HashMap,
+};
+
+/// How a lint is reported: not at all, as a warning, or as an error that fails the run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[derive(Debug)]
+pub struct Lint {
+    pub name: &'static str,
+    pub default: Level,
+}
+
+/// Identifiers containing non-ASCII characters, which can be hard to type or tell apart.
+pub const NON_ASCII_IDENTS: Lint = Lint {
+    name: "non-ascii-idents",
+    default: Level::Allow,
+};
+
+pub const LINTS: &[&Lint] = &[&NON_ASCII_IDENTS];
+
+/// Per-lint levels chosen with `-A`, `-W` and `-D`, falling back to each lint's default.
+#[derive(Debug, Clone, Default)]
+pub struct LintLevels {
+    overrides: HashMap<&'static str, Level>,
+}
+
+impl LintLevels {
+    pub fn set(&mut self, name: &str, level: Level) -> Result<(), String> {
+        let lint = LINTS
+            .iter()
+            .find(|lint| lint.name == name)
+            .ok_or_else(|| message(Key::UnknownLint, &[&name]))?;
+        self.overrides.insert(lint.name, level);
+        Ok(())
+    }
+
+    pub fn level(&self, lint: &Lint) -> Level {
+        self.overrides
+            .get(lint.name)
+            .copied()
+            .unwrap_or(lint.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_fall_back_to_defaults() {
+        let mut levels = LintLevels::default();
+        assert_eq!(levels.level(&NON_ASCII_IDENTS), Level::Allow);
+        levels.set("non-ascii-idents", Level::Deny).unwrap();
+        assert_eq!(levels.level(&NON_ASCII_IDENTS), Level::Deny);
+    }
+
+    #[test]
+    fn rejects_unknown_lints() {
+        let mut levels = LintLevels::default();
+        let err = levels.set("no-such-lint", Level::Warn).unwrap_err();
+        assert!(err.contains("no-such-lint"));
+    }
+}
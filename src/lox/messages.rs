@@ -0,0 +1,273 @@
+use std::{
+    cell::Cell,
+    fmt::Display,
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+static DEFAULT_LANG: AtomicU8 = AtomicU8::new(Lang::En as u8);
+
+thread_local! {
+    /// The language of the interpreter currently running on this thread, if it chose one.
+    static SCOPED_LANG: Cell<Option<Lang>> = const { Cell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Es,
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Lang::En),
+            "es" => Ok(Lang::Es),
+            _ => Err(message(Key::UnknownLang, &[&s])),
+        }
+    }
+}
+
+/// Sets the language for interpreters that were not given one with `Interpreter::with_lang`,
+/// and for argument errors. Each call replaces the previous language.
+pub fn set_lang(lang: Lang) {
+    DEFAULT_LANG.store(lang as u8, Ordering::Relaxed);
+}
+
+pub(crate) fn lang() -> Lang {
+    SCOPED_LANG
+        .get()
+        .unwrap_or(match DEFAULT_LANG.load(Ordering::Relaxed) {
+            1 => Lang::Es,
+            _ => Lang::En,
+        })
+}
+
+/// Runs `f` with messages in `lang`, or in the current language if it is `None`.
+pub(crate) fn with_lang<R>(lang: Option<Lang>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Lang>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            SCOPED_LANG.set(self.0);
+        }
+    }
+
+    let _restore = Restore(SCOPED_LANG.replace(lang.or(SCOPED_LANG.get())));
+    f()
+}
+
+/// The command-line usage text of the `interpreter-rs` binary, in the default language.
+pub fn usage() -> String {
+    message(Key::Usage, &[])
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Key {
+    ErrorHeader,
+    WarningHeader,
+    Here,
+    Note,
+    Help,
+    UnclosedString,
+    InputEndsHere,
+    CloseString,
+    UnterminatedString,
+    InvalidNumber,
+    UnexpectedChar,
+    Welcome,
+    Usage,
+    UnknownLang,
+    UnknownColor,
+    UnknownLint,
+    UnknownErrorFormat,
+    NonAsciiIdent,
+    RuntimeError,
+    InternalErrorSummary,
+    InternalError,
+    CrashReportFailed,
+    ReplHelp,
+    UnknownCommand,
+    SessionSaved,
+    FileError,
+}
+
+impl Key {
+    fn template(self, lang: Lang) -> &'static str {
+        match (self, lang) {
+            (Key::ErrorHeader, Lang::En) => "Error[{}]: {}",
+            (Key::ErrorHeader, Lang::Es) => "Error[{}]: {}",
+            (Key::WarningHeader, Lang::En) => "Warning[{}]: {}",
+            (Key::WarningHeader, Lang::Es) => "Aviso[{}]: {}",
+            (Key::Here, Lang::En) => "Here",
+            (Key::Here, Lang::Es) => "Aquí",
+            (Key::Note, Lang::En) => "note: {}",
+            (Key::Note, Lang::Es) => "nota: {}",
+            (Key::Help, Lang::En) => "help: {}",
+            (Key::Help, Lang::Es) => "ayuda: {}",
+            (Key::UnclosedString, Lang::En) => "this string is never closed",
+            (Key::UnclosedString, Lang::Es) => "esta cadena nunca se cierra",
+            (Key::InputEndsHere, Lang::En) => "input ends here",
+            (Key::InputEndsHere, Lang::Es) => "la entrada termina aquí",
+            (Key::CloseString, Lang::En) => "add a closing \" to end the string",
+            (Key::CloseString, Lang::Es) => "añade unas comillas \" para cerrar la cadena",
+            (Key::UnterminatedString, Lang::En) => "Unterminated string.",
+            (Key::UnterminatedString, Lang::Es) => "Cadena sin terminar.",
+            (Key::InvalidNumber, Lang::En) => "Invalid number: {}.",
+            (Key::InvalidNumber, Lang::Es) => "Número inválido: {}.",
+            (Key::UnexpectedChar, Lang::En) => "Unexpected \"{}\" character.",
+            (Key::UnexpectedChar, Lang::Es) => "Carácter \"{}\" inesperado.",
+            (Key::Welcome, Lang::En) => "=== Welcome to the Lox REPL ===",
+            (Key::Welcome, Lang::Es) => "=== Bienvenido al REPL de Lox ===",
+            (Key::Usage, Lang::En) => {
+                "Usage is: cargo run [options] [path/to/script] [-- args...]\n\n\
+                 Options:\n\
+                 \x20 -e, --eval <source>           Run <source> instead of a script\n\
+                 \x20 --watch                       Re-run the script whenever it changes\n\
+                 \x20 --check                       Report diagnostics without running\n\
+                 \x20 --dump-tokens                 Print the scanned tokens\n\
+                 \x20 --time                        Print the time spent in each phase\n\
+                 \x20 --lang <en|es>                Language of diagnostics\n\
+                 \x20 --color=<auto|always|never>   Colorize diagnostics\n\
+                 \x20 --error-format=<human|json>   Print diagnostics as text or JSON lines\n\
+                 \x20 -A, -W, -D <lint>             Allow, warn on or deny a lint\n\
+                 \x20 --prompt <text>               REPL prompt (or LOX_PROMPT)\n\
+                 \x20 --banner <text>, --no-banner  REPL banner (or LOX_BANNER)"
+            }
+            (Key::Usage, Lang::Es) => {
+                "Uso: cargo run [opciones] [ruta/al/script] [-- args...]\n\n\
+                 Opciones:\n\
+                 \x20 -e, --eval <código>           Ejecuta <código> en lugar de un script\n\
+                 \x20 --watch                       Vuelve a ejecutar el script cuando cambia\n\
+                 \x20 --check                       Muestra los diagnósticos sin ejecutar\n\
+                 \x20 --dump-tokens                 Muestra los tokens escaneados\n\
+                 \x20 --time                        Muestra el tiempo de cada fase\n\
+                 \x20 --lang <en|es>                Idioma de los diagnósticos\n\
+                 \x20 --color=<auto|always|never>   Colorea los diagnósticos\n\
+                 \x20 --error-format=<human|json>   Muestra los diagnósticos como texto o JSON\n\
+                 \x20 -A, -W, -D <lint>             Permite, avisa o rechaza un lint\n\
+                 \x20 --prompt <texto>              Indicador del REPL (o LOX_PROMPT)\n\
+                 \x20 --banner <texto>, --no-banner Mensaje de bienvenida (o LOX_BANNER)"
+            }
+            (Key::UnknownLang, Lang::En) => "Unknown language \"{}\".",
+            (Key::UnknownLang, Lang::Es) => "Idioma \"{}\" desconocido.",
+            (Key::UnknownColor, Lang::En) => {
+                "Unknown color setting \"{}\"; expected auto, always or never."
+            }
+            (Key::UnknownColor, Lang::Es) => {
+                "Opción de color \"{}\" desconocida; se esperaba auto, always o never."
+            }
+            (Key::UnknownErrorFormat, Lang::En) => {
+                "Unknown error format \"{}\"; expected human or json."
+            }
+            (Key::UnknownErrorFormat, Lang::Es) => {
+                "Formato de error \"{}\" desconocido; se esperaba human o json."
+            }
+            (Key::UnknownLint, Lang::En) => "Unknown lint \"{}\".",
+            (Key::UnknownLint, Lang::Es) => "Lint \"{}\" desconocido.",
+            (Key::NonAsciiIdent, Lang::En) => "Identifier \"{}\" contains non-ASCII characters.",
+            (Key::NonAsciiIdent, Lang::Es) => {
+                "El identificador \"{}\" contiene caracteres no ASCII."
+            }
+            (Key::RuntimeError, Lang::En) => "Runtime error",
+            (Key::RuntimeError, Lang::Es) => "Error de ejecución",
+            (Key::InternalErrorSummary, Lang::En) => "Internal error",
+            (Key::InternalErrorSummary, Lang::Es) => "Error interno",
+            (Key::InternalError, Lang::En) => {
+                "The interpreter hit an internal bug; this is not a problem with your script. A crash report was written to {}."
+            }
+            (Key::InternalError, Lang::Es) => {
+                "El intérprete encontró un error interno; no es un problema de tu script. Se escribió un informe en {}."
+            }
+            (Key::CrashReportFailed, Lang::En) => {
+                "The interpreter hit an internal bug, and the crash report could not be written: {}."
+            }
+            (Key::CrashReportFailed, Lang::Es) => {
+                "El intérprete encontró un error interno y no se pudo escribir el informe: {}."
+            }
+            (Key::ReplHelp, Lang::En) => {
+                "Commands:\n  :help         Show this message\n  :save <file>  Write the inputs that ran successfully to a file\n  :load <file>  Run a file in this session\n  :quit         Exit the REPL"
+            }
+            (Key::ReplHelp, Lang::Es) => {
+                "Comandos:\n  :help           Muestra este mensaje\n  :save <fichero> Guarda en un fichero las entradas ejecutadas sin errores\n  :load <fichero> Ejecuta un fichero en esta sesión\n  :quit           Sale del REPL"
+            }
+            (Key::SessionSaved, Lang::En) => "Session saved to {}.",
+            (Key::SessionSaved, Lang::Es) => "Sesión guardada en {}.",
+            (Key::FileError, Lang::En) => "Could not access \"{}\": {}.",
+            (Key::FileError, Lang::Es) => "No se pudo acceder a \"{}\": {}.",
+            (Key::UnknownCommand, Lang::En) => "Unknown command \"{}\". Type :help for a list.",
+            (Key::UnknownCommand, Lang::Es) => {
+                "Comando \"{}\" desconocido. Escribe :help para ver la lista."
+            }
+        }
+    }
+}
+
+/// Renders the template for `key` in the current language, substituting each `{}` in order.
+pub fn message(key: Key, args: &[&dyn Display]) -> String {
+    message_in(lang(), key, args)
+}
+
+pub(crate) fn message_in(lang: Lang, key: Key, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    let mut parts = key.template(lang).split("{}");
+    let mut out = String::from(parts.next().unwrap_or_default());
+    for part in parts {
+        if let Some(arg) = args.next() {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_placeholders_in_order() {
+        let msg = with_lang(Some(Lang::En), || {
+            message(Key::ErrorHeader, &[&"E0001", &"Unterminated"])
+        });
+        assert_eq!(msg, "Error[E0001]: Unterminated");
+    }
+
+    #[test]
+    fn leaves_missing_arguments_empty() {
+        let msg = message_in(Lang::En, Key::ErrorHeader, &[&"E0001"]);
+        assert_eq!(msg, "Error[E0001]: ");
+    }
+
+    #[test]
+    fn renders_spanish_templates() {
+        assert_eq!(
+            message_in(Lang::Es, Key::WarningHeader, &[&"lint", &"msg"]),
+            "Aviso[lint]: msg"
+        );
+        assert_eq!(
+            message_in(Lang::Es, Key::UnexpectedChar, &[&'@']),
+            "Carácter \"@\" inesperado."
+        );
+    }
+
+    #[test]
+    fn scoped_language_is_restored() {
+        let outer = lang();
+        with_lang(Some(Lang::Es), || {
+            assert_eq!(lang(), Lang::Es);
+            with_lang(None, || assert_eq!(lang(), Lang::Es));
+            with_lang(Some(Lang::En), || assert_eq!(lang(), Lang::En));
+            assert_eq!(lang(), Lang::Es);
+        });
+        assert_eq!(lang(), outer);
+    }
+
+    #[test]
+    fn parses_language_names() {
+        assert_eq!("es".parse::<Lang>(), Ok(Lang::Es));
+        assert!("fr".parse::<Lang>().is_err());
+    }
+}
@@ -1,7 +1,41 @@
+#[cfg(feature = "deterministic")]
+use std::hash::BuildHasher;
+
+#[cfg(feature = "deterministic")]
+use siphasher::sip::SipHasher13;
+
 use self::error::LoxError;
 
+pub(crate) mod crash;
 pub mod error;
+pub mod input;
 pub mod interpreter;
+pub mod lint;
+pub mod messages;
+pub mod repl;
 pub mod scanner;
 
-pub type Result<'a, T> = std::result::Result<T, LoxError<'a>>;
+pub type Result<T> = std::result::Result<T, LoxError>;
+
+/// Map type for all interpreter-internal tables. With the `deterministic` feature it hashes with
+/// SipHash-1-3 under fixed zero keys, a specified algorithm from the `siphasher` crate, so
+/// iteration order is identical from run to run and across Rust releases. std's `DefaultHasher`
+/// would not do: its algorithm may change between releases.
+#[cfg(feature = "deterministic")]
+pub type HashMap<K, V> = std::collections::HashMap<K, V, FixedState>;
+#[cfg(not(feature = "deterministic"))]
+pub type HashMap<K, V> = std::collections::HashMap<K, V>;
+
+/// Builds SipHash-1-3 hashers keyed with zeros.
+#[cfg(feature = "deterministic")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FixedState;
+
+#[cfg(feature = "deterministic")]
+impl BuildHasher for FixedState {
+    type Hasher = SipHasher13;
+
+    fn build_hasher(&self) -> SipHasher13 {
+        SipHasher13::new_with_keys(0, 0)
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    fs,
+    io::{self, Write},
+};
+
+use super::{
+    interpreter::Interpreter,
+    messages::{self, message, Key},
+    scanner::Parser,
+};
+
+/// An interactive read-eval-print loop over an `Interpreter`.
+///
+/// Lines are read from the interpreter's `InputSource`, and everything the REPL itself prints
+/// (banner, prompts and command output) goes to `output`. Program output and diagnostics go to
+/// the interpreter's own writers; see `Interpreter::with_output` and `with_error_output`.
+pub struct Repl<W> {
+    interpreter: Interpreter,
+    output: W,
+    prompt: String,
+    banner: Option<String>,
+    /// Inputs that ran without errors, in order, for `:save`.
+    session: String,
+}
+
+impl Repl<io::Stdout> {
+    pub fn stdio(interpreter: Interpreter) -> Self {
+        Self::new(interpreter, io::stdout())
+    }
+}
+
+impl<W: Write> Repl<W> {
+    pub fn new(interpreter: Interpreter, output: W) -> Self {
+        let banner = messages::with_lang(interpreter.lang(), || message(Key::Welcome, &[]));
+        Self {
+            interpreter,
+            output,
+            prompt: String::from("  > "),
+            banner: Some(banner),
+            session: String::new(),
+        }
+    }
+
+    /// Replaces the `  > ` prompt.
+    pub fn with_prompt(mut self, prompt: String) -> Self {
+        self.prompt = prompt;
+        self
+    }
+
+    /// Replaces the welcome banner, or suppresses it with `None`.
+    pub fn with_banner(mut self, banner: Option<String>) -> Self {
+        self.banner = banner;
+        self
+    }
+
+    pub fn interpreter(&mut self) -> &mut Interpreter {
+        &mut self.interpreter
+    }
+
+    /// Runs until the input is exhausted or the user enters `:quit`.
+    pub fn run(&mut self) -> io::Result<()> {
+        // Prompts and the banner would only clutter the output when input is piped in.
+        let interactive = self.interpreter.is_interactive();
+        let mut input = String::new();
+        if interactive {
+            if let Some(banner) = &self.banner {
+                writeln!(self.output, "{}", banner)?;
+            }
+        }
+        loop {
+            if interactive {
+                let prompt = if input.is_empty() {
+                    &self.prompt
+                } else {
+                    "... "
+                };
+                write!(self.output, "{}", prompt)?;
+                self.output.flush()?;
+            }
+            let line = self.interpreter.read_line();
+            let Some(line) = line else {
+                if !input.is_empty() {
+                    let _ = self.interpreter.run(&input);
+                }
+                return Ok(());
+            };
+
+            if input.is_empty() && line.trim_start().starts_with(':') {
+                let lang = self.interpreter.lang();
+                if !messages::with_lang(lang, || self.command(line.trim()))? {
+                    return Ok(());
+                }
+                continue;
+            }
+            input.push_str(&line);
+            input.push('\n');
+            if !Parser::is_incomplete(&input) {
+                if self.interpreter.run(&input).is_ok() {
+                    self.session.push_str(&input);
+                }
+                input.clear();
+            }
+        }
+    }
+
+    /// Handles a `:command`, returning `false` if the REPL should exit.
+    fn command(&mut self, line: &str) -> io::Result<bool> {
+        let (command, arg) = match line.split_once(' ') {
+            Some((command, arg)) => (command, arg.trim()),
+            None => (line, ""),
+        };
+        match command {
+            ":quit" => return Ok(false),
+            ":help" => writeln!(self.output, "{}", message(Key::ReplHelp, &[]))?,
+            ":save" => match fs::write(arg, &self.session) {
+                Ok(()) => writeln!(self.output, "{}", message(Key::SessionSaved, &[&arg]))?,
+                Err(err) => writeln!(self.output, "{}", message(Key::FileError, &[&arg, &err]))?,
+            },
+            ":load" => match fs::read_to_string(arg) {
+                Ok(source) => {
+                    if self.interpreter.run(&source).is_ok() {
+                        self.session.push_str(&source);
+                        if !source.ends_with('\n') {
+                            self.session.push('\n');
+                        }
+                    }
+                }
+                Err(err) => writeln!(self.output, "{}", message(Key::FileError, &[&arg, &err]))?,
+            },
+            _ => writeln!(self.output, "{}", message(Key::UnknownCommand, &[&command]))?,
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env,
+        io::Cursor,
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+
+    /// A writer whose contents can still be read after it is moved into an interpreter.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn contents(&self) -> String {
+            String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+        }
+    }
+
+    /// Runs a REPL over `input`, returning what the REPL printed and what the programs printed.
+    fn run(input: &str) -> (String, String) {
+        let program = Shared::default();
+        let interpreter = Interpreter::new()
+            .with_input(Cursor::new(input.to_string()))
+            .with_output(program.clone())
+            .with_error_output(Vec::new())
+            .with_dump_tokens(true);
+        let mut repl_output = Vec::new();
+        Repl::new(interpreter, &mut repl_output).run().unwrap();
+        (String::from_utf8(repl_output).unwrap(), program.contents())
+    }
+
+    fn runs(program_output: &str) -> usize {
+        program_output
+            .lines()
+            .filter(|line| line.contains("\tEof\t"))
+            .count()
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("lox-repl-test-{}-{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn continues_unclosed_strings_and_braces() {
+        let (_, output) = run("print \"a\nb\";\n{\nvar x;\n}\nprint (1 +\n2);\n");
+        assert_eq!(runs(&output), 3);
+        assert!(output.contains("\"a\\nb\""));
+    }
+
+    #[test]
+    fn quit_stops_the_loop() {
+        let (_, output) = run("var a;\n:quit\nvar b;\n");
+        assert_eq!(runs(&output), 1);
+        assert!(!output.contains("\"b\""));
+    }
+
+    #[test]
+    fn save_and_load_keep_only_successful_inputs() {
+        let good = temp_path("good.lox");
+        let bad = temp_path("bad.lox");
+        let saved = temp_path("saved.lox");
+        fs::write(&good, "var c;").unwrap();
+        fs::write(&bad, "@").unwrap();
+
+        let input = format!(
+            "var a;\n@\n:load {}\n:load {}\nvar b;\n:save {}\n",
+            good.display(),
+            bad.display(),
+            saved.display()
+        );
+        let (repl_output, _) = run(&input);
+        let session = fs::read_to_string(&saved).unwrap();
+        for path in [good, bad, saved] {
+            let _ = fs::remove_file(path);
+        }
+
+        assert_eq!(session, "var a;\nvar c;\nvar b;\n");
+        assert!(repl_output.contains("lox-repl-test"));
+    }
+
+    #[test]
+    fn no_banner_or_prompt_when_not_interactive() {
+        let (repl_output, output) = run("var a;\n");
+        assert_eq!(repl_output, "");
+        assert_eq!(runs(&output), 1);
+    }
+}
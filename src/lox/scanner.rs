@@ -1,12 +1,19 @@
-use std::{collections::HashMap, hash::Hash, str::Chars, sync::OnceLock};
+use std::{fmt::Display, str::Chars, sync::OnceLock};
 
 use itertools::{peek_nth, PeekNth};
+use unicode_xid::UnicodeXID;
 
-use super::error::ParseError;
+use super::{
+    crash,
+    error::{columns, json_string, Diagnostic, Label, Severity},
+    lint::{Level, Lint, LintLevels, NON_ASCII_IDENTS},
+    messages::{message, Key},
+    HashMap,
+};
 
 static KEYWORDS: OnceLock<HashMap<&str, TokenType>> = OnceLock::new();
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenType {
     // Single-character tokens.
     LeftParen,
@@ -54,7 +61,7 @@ pub enum TokenType {
     Var,
     While,
 
-    EOF,
+    Eof,
 }
 
 #[derive(Debug)]
@@ -69,12 +76,81 @@ pub struct Token<'a> {
     t_type: TokenType,
     lexeme: &'a str,
     literal: Option<Literal<'a>>,
+    /// 1-based column of the token's first character, with tabs counted as four columns.
     col: usize,
+    /// Line the token starts on.
     line: usize,
+    /// Whitespace and comments between the previous token's trailing trivia and this token.
+    /// Only populated when the scanner was created with `with_trivia`.
+    leading_trivia: &'a str,
+    /// Whitespace and comments after this token up to (not including) the end of its line.
+    trailing_trivia: &'a str,
+}
+
+impl<'a> Token<'a> {
+    pub fn t_type(&self) -> &TokenType {
+        &self.t_type
+    }
+
+    pub fn lexeme(&self) -> &'a str {
+        self.lexeme
+    }
+
+    pub fn literal(&self) -> Option<&Literal<'a>> {
+        self.literal.as_ref()
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn col(&self) -> usize {
+        self.col
+    }
+
+    pub fn leading_trivia(&self) -> &'a str {
+        self.leading_trivia
+    }
+
+    pub fn trailing_trivia(&self) -> &'a str {
+        self.trailing_trivia
+    }
+}
+
+impl<'a> Display for Literal<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::String(s) => write!(f, "{}", json_string(s)),
+            Self::Number(n) => write!(f, "{}", n),
+            Self::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+impl<'a> Display for Token<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}\t{:?}\t{}\t",
+            self.line,
+            self.col,
+            self.t_type,
+            json_string(self.lexeme)
+        )?;
+        match &self.literal {
+            Some(literal) => write!(f, "{}", literal),
+            None => write!(f, "nil"),
+        }
+    }
 }
 
 pub struct Parser<'a> {
-    had_error: bool,
+    errors: Vec<Diagnostic>,
+    warnings: Vec<Diagnostic>,
+    lints: LintLevels,
+    unterminated_string: bool,
+    keep_trivia: bool,
+    trivia_start: usize,
     source: &'a str,
     chars: PeekNth<Chars<'a>>,
     tokens: Vec<Token<'a>>,
@@ -83,12 +159,22 @@ pub struct Parser<'a> {
     line_start: usize,
     col: usize,
     line: usize,
+    /// Position of the token being scanned, so tokens and diagnostics point at where it began
+    /// rather than where scanning stopped.
+    start_line_start: usize,
+    start_col: usize,
+    start_line: usize,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(source: &'a str) -> Self {
         Self {
-            had_error: false,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            lints: LintLevels::default(),
+            unterminated_string: false,
+            keep_trivia: false,
+            trivia_start: 0,
             source,
             chars: peek_nth(source.chars()),
             tokens: Vec::new(),
@@ -97,30 +183,89 @@ impl<'a> Parser<'a> {
             line_start: 0,
             col: 0,
             line: 1,
+            start_line_start: 0,
+            start_col: 0,
+            start_line: 1,
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Result<&Vec<Token>, ()> {
+    /// Attaches whitespace and comments to the surrounding tokens so the source can be
+    /// reproduced exactly by concatenating each token's leading trivia, lexeme and trailing trivia.
+    pub fn with_trivia(mut self) -> Self {
+        self.keep_trivia = true;
+        self
+    }
+
+    pub fn with_lints(mut self, lints: LintLevels) -> Self {
+        self.lints = lints;
+        self
+    }
+
+    /// Lint warnings from the last scan; denied lints are reported as errors instead.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    pub fn scan_tokens(&mut self) -> Result<&Vec<Token<'a>>, &Vec<Diagnostic>> {
         while self.chars.peek().is_some() {
-            self.start = self.current;
+            self.mark_start();
+            crash::set_position(self.line, self.col + 1, self.tokens.len());
             self.scan_token();
         }
 
-        if !self.had_error {
+        self.mark_start();
+        self.add_token(TokenType::Eof, None);
+
+        if self.errors.is_empty() {
             Ok(&self.tokens)
         } else {
-            Err(())
+            Err(&self.errors)
         }
     }
 
+    /// Whether `source` stops in the middle of a string literal or with unclosed parentheses or
+    /// braces, meaning more input is needed before it can be run.
+    pub fn is_incomplete(source: &str) -> bool {
+        let mut parser = Parser::new(source);
+        let _ = parser.scan_tokens();
+        let depth = parser
+            .tokens
+            .iter()
+            .fold(0isize, |depth, token| match token.t_type {
+                TokenType::LeftParen | TokenType::LeftBrace => depth + 1,
+                TokenType::RightParen | TokenType::RightBrace => depth - 1,
+                _ => depth,
+            });
+        parser.unterminated_string || depth > 0
+    }
+
+    fn mark_start(&mut self) {
+        self.start = self.current;
+        self.start_line_start = self.line_start;
+        self.start_col = self.col;
+        self.start_line = self.line;
+    }
+
     fn lexeme(&self) -> &str {
-        &self.source[self.line_start..self.current]
+        &self.source[self.start..self.current]
+    }
+
+    /// The whole line the current token starts on.
+    fn start_line_text(&self) -> &'a str {
+        let rest = &self.source[self.start_line_start..];
+        rest.split('\n').next().unwrap_or(rest)
+    }
+
+    /// Columns the current lexeme covers on its first line, counting tabs as the scanner does.
+    fn lexeme_width(&self) -> usize {
+        let lexeme = &self.source[self.start..self.current];
+        lexeme.chars().take_while(|&c| c != '\n').map(columns).sum()
     }
 
     fn check_keyword(&self, key: &str) -> Option<&TokenType> {
         KEYWORDS
             .get_or_init(|| {
-                let mut keywords = HashMap::new();
+                let mut keywords = HashMap::default();
                 keywords.insert("and", TokenType::And);
                 keywords.insert("class", TokenType::Class);
                 keywords.insert("else", TokenType::Else);
@@ -142,22 +287,68 @@ impl<'a> Parser<'a> {
             .get(key)
     }
 
-    fn record_error(&mut self, msg: String) {
-        self.had_error = true;
-        eprintln!(
-            "{}",
-            ParseError::new(msg, self.lexeme(), self.col, self.line,)
+    fn record_error(&mut self, code: &'static str, msg: String) {
+        self.errors
+            .push(self.diagnostic(Severity::Error, code, msg));
+    }
+
+    fn lint(&mut self, lint: &Lint, msg: String) {
+        match self.lints.level(lint) {
+            Level::Allow => {}
+            Level::Warn => {
+                let warning = self.diagnostic(Severity::Warning, lint.name, msg);
+                self.warnings.push(warning);
+            }
+            Level::Deny => {
+                let error = self.diagnostic(Severity::Error, lint.name, msg);
+                self.errors.push(error);
+            }
+        }
+    }
+
+    fn diagnostic(&self, severity: Severity, code: &'static str, msg: String) -> Diagnostic {
+        Diagnostic::new(
+            severity,
+            code,
+            msg,
+            self.start_line_text(),
+            self.start_col + 1,
+            self.lexeme_width(),
+            self.start_line,
         )
     }
 
     fn add_token(&mut self, t_type: TokenType, literal: Option<Literal<'a>>) {
+        let leading_trivia = if self.keep_trivia {
+            self.split_trivia()
+        } else {
+            ""
+        };
         self.tokens.push(Token {
             t_type,
             lexeme: &self.source[self.start..self.current],
             literal,
-            col: self.col,
-            line: self.line,
+            col: self.start_col + 1,
+            line: self.start_line,
+            leading_trivia,
+            trailing_trivia: "",
         });
+        self.trivia_start = self.current;
+    }
+
+    /// Splits the trivia before the current token: the part on the previous token's line becomes
+    /// its trailing trivia and the rest is returned as the current token's leading trivia.
+    fn split_trivia(&mut self) -> &'a str {
+        let source = self.source;
+        let trivia = &source[self.trivia_start..self.start];
+        match self.tokens.last_mut() {
+            Some(prev) => {
+                let split = trivia.find('\n').unwrap_or(trivia.len());
+                prev.trailing_trivia = &trivia[..split];
+                &trivia[split..]
+            }
+            None => trivia,
+        }
     }
 
     fn matches(&mut self, pred: impl FnOnce(&char) -> bool) -> Option<char> {
@@ -165,17 +356,16 @@ impl<'a> Parser<'a> {
             .next_if(pred)
             .map(|c| {
                 self.current += c.len_utf8();
-                self.col += 1;
+                self.col += columns(c);
                 Some(c)
             })
             .unwrap_or(None)
     }
 
     fn advance(&mut self) -> Option<char> {
-        self.chars.next().map(|c| {
+        self.chars.next().inspect(|&c| {
             self.current += c.len_utf8();
-            self.col += 1;
-            c
+            self.col += columns(c);
         })
     }
 
@@ -196,15 +386,40 @@ impl<'a> Parser<'a> {
                 )),
             )
         } else {
-            self.record_error(format!("Unterminated string: {}.", self.lexeme()));
+            self.unterminated_string = true;
+            let mut error = self
+                .diagnostic(
+                    Severity::Error,
+                    "E0001",
+                    message(Key::UnterminatedString, &[]),
+                )
+                .with_label(message(Key::UnclosedString, &[]))
+                .with_help(message(Key::CloseString, &[]));
+            if self.line != self.start_line {
+                let last_line = &self.source[self.line_start..];
+                error = error.with_secondary(Label::new(
+                    last_line,
+                    self.col + 1,
+                    1,
+                    self.line,
+                    message(Key::InputEndsHere, &[]),
+                ));
+            }
+            self.errors.push(error);
         }
     }
 
     fn identifier(&mut self) {
-        while self.matches(|&c| c.is_alphanumeric() || c == '_').is_some() {}
+        while self.matches(|&c| c.is_xid_continue()).is_some() {}
         if let Some(t_type) = self.check_keyword(self.lexeme()) {
             self.add_token(t_type.clone(), None)
         } else {
+            if !self.lexeme().is_ascii() {
+                self.lint(
+                    &NON_ASCII_IDENTS,
+                    message(Key::NonAsciiIdent, &[&self.lexeme()]),
+                );
+            }
             self.add_token(TokenType::Ident, None);
         }
     }
@@ -222,7 +437,7 @@ impl<'a> Parser<'a> {
         if let Ok(number) = self.lexeme().parse::<f64>() {
             self.add_token(TokenType::Number, Some(Literal::Number(number)))
         } else {
-            self.record_error(format!("Invalid number: {}.", self.lexeme()));
+            self.record_error("E0002", message(Key::InvalidNumber, &[&self.lexeme()]));
         }
     }
 
@@ -274,7 +489,7 @@ impl<'a> Parser<'a> {
                         self.add_token(TokenType::Greater, None);
                     }
                 }
-                c if c.is_alphabetic() => self.identifier(),
+                c if c == '_' || c.is_xid_start() => self.identifier(),
                 c if c.is_numeric() => self.number(),
                 '"' => self.string(),
                 '\n' => {
@@ -282,10 +497,9 @@ impl<'a> Parser<'a> {
                     self.line_start = self.current;
                     self.col = 0;
                 }
-                '\t' => self.col += 3,
-                ' ' | '\r' => {}
+                ' ' | '\t' | '\r' => {}
                 _ => {
-                    self.record_error(format!("Unexpected \"{}\" character.", c));
+                    self.record_error("E0003", message(Key::UnexpectedChar, &[&c]));
                 }
             }
             true
@@ -294,3 +508,134 @@ impl<'a> Parser<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lexemes(source: &str) -> Vec<(TokenType, &str)> {
+        let mut parser = Parser::new(source);
+        let tokens = parser.scan_tokens().expect("source should scan");
+        tokens
+            .iter()
+            .map(|t| (t.t_type.clone(), t.lexeme))
+            .collect()
+    }
+
+    #[test]
+    fn scans_unicode_identifiers() {
+        for name in ["café", "名前", "_x"] {
+            let source = format!("var {} = 1;", name);
+            assert_eq!(
+                lexemes(&source),
+                [
+                    (TokenType::Var, "var"),
+                    (TokenType::Ident, name),
+                    (TokenType::Equal, "="),
+                    (TokenType::Number, "1"),
+                    (TokenType::Semicolon, ";"),
+                    (TokenType::Eof, ""),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_identifier_starting_with_non_xid_start() {
+        let mut parser = Parser::new("·x");
+        let errors = parser
+            .scan_tokens()
+            .expect_err("· cannot start an identifier");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code(), "E0003");
+        assert_eq!(errors[0].col(), 1);
+        assert_eq!(
+            parser.tokens.iter().map(|t| t.lexeme).collect::<Vec<_>>(),
+            ["x", ""]
+        );
+    }
+
+    fn lint_levels(level: Level) -> LintLevels {
+        let mut levels = LintLevels::default();
+        levels.set(NON_ASCII_IDENTS.name, level).unwrap();
+        levels
+    }
+
+    #[test]
+    fn non_ascii_idents_allowed_by_default() {
+        let mut parser = Parser::new("var café;");
+        assert!(parser.scan_tokens().is_ok());
+        assert!(parser.warnings().is_empty());
+    }
+
+    #[test]
+    fn warned_lint_still_scans() {
+        let mut parser = Parser::new("var café;").with_lints(lint_levels(Level::Warn));
+        assert!(parser.scan_tokens().is_ok());
+        assert_eq!(parser.warnings().len(), 1);
+        assert_eq!(parser.warnings()[0].severity(), Severity::Warning);
+        assert_eq!(parser.warnings()[0].code(), NON_ASCII_IDENTS.name);
+    }
+
+    #[test]
+    fn denied_lint_fails_the_scan() {
+        let mut parser = Parser::new("var café;").with_lints(lint_levels(Level::Deny));
+        let errors = parser.scan_tokens().expect_err("denied lint is an error");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].severity(), Severity::Error);
+        assert_eq!(errors[0].code(), NON_ASCII_IDENTS.name);
+        assert!(parser.warnings().is_empty());
+    }
+
+    #[test]
+    fn dumped_tokens_quote_strings_as_json() {
+        let mut parser = Parser::new("\"a\tb\n\u{1}\"");
+        let tokens = parser.scan_tokens().expect("source should scan");
+        assert_eq!(
+            tokens[0].to_string(),
+            "1:1\tString\t\"\\\"a\\tb\\n\\u0001\\\"\"\t\"a\\tb\\n\\u0001\""
+        );
+    }
+
+    #[test]
+    fn trivia_reproduces_source() {
+        let sources = [
+            "",
+            "  \n\t \n",
+            "// only a comment",
+            "var a = 1; // trailing\n\n\n// leading\nprint a;\n",
+            "\tvar\tb\t=\t\"x\ty\";\t\n",
+            "\n\n  print (1 + 2) * 3;  \n  // done",
+        ];
+        for source in sources {
+            let mut parser = Parser::new(source).with_trivia();
+            let tokens = parser.scan_tokens().expect("source should scan");
+            let rebuilt = tokens
+                .iter()
+                .map(|t| format!("{}{}{}", t.leading_trivia, t.lexeme, t.trailing_trivia))
+                .collect::<String>();
+            assert_eq!(rebuilt, source);
+        }
+    }
+
+    #[test]
+    fn json_columns_count_characters() {
+        let mut parser = Parser::new("\t\tvar é = @;");
+        let errors = parser.scan_tokens().expect_err("@ is not a token");
+        assert_eq!(errors[0].col(), 17);
+        assert!(errors[0]
+            .to_json()
+            .contains("\"col\":11,\"span\":{\"start\":11,\"end\":12}"));
+    }
+
+    #[test]
+    fn json_notes_keep_their_kind() {
+        let mut parser = Parser::new("print \"x");
+        let errors = parser
+            .scan_tokens()
+            .expect_err("the string is never closed");
+        assert!(errors[0]
+            .to_json()
+            .contains("\"notes\":[{\"kind\":\"help\",\"message\":"));
+    }
+}
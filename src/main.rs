@@ -1,16 +1,171 @@
-use std::env;
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::Path,
+    process, thread,
+    time::Duration,
+};
 
-mod lox;
+use lox_interpreter::{
+    set_lang, ColorChoice, ErrorFormat, Interpreter, Lang, Level, LintLevels, Repl,
+};
 
-use lox::interpreter::Interpreter;
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(200);
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(50);
+
+fn usage() -> ! {
+    eprintln!("{}", lox_interpreter::usage());
+    process::exit(64);
+}
+
+/// Exits with the error's status code if the run failed.
+fn exit_on_error(result: lox_interpreter::Result<()>) {
+    if let Err(err) = result {
+        process::exit(err.exit_code());
+    }
+}
+
+/// Re-runs the script in a fresh interpreter, on a cleared screen, whenever it changes on disk.
+/// Read errors are reported through the interpreter, like its diagnostics.
+fn watch_file(interpreter: &Interpreter, path: &Path) -> ! {
+    let modified = || fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last_modified = None;
+    loop {
+        let current = modified();
+        if current != last_modified {
+            // Editors often write a file in several steps; wait until it stops changing.
+            thread::sleep(WATCH_DEBOUNCE);
+            if modified() == current {
+                last_modified = current;
+                print!("\x1b[2J\x1b[H");
+                let _ = io::stdout().flush();
+                let _ = interpreter.clone().run_file(path);
+            }
+        }
+        thread::sleep(WATCH_POLL_INTERVAL);
+    }
+}
+
+/// Starts the REPL; an empty `banner` suppresses it.
+fn run_repl(interpreter: Interpreter, prompt: Option<String>, banner: Option<String>) {
+    let mut repl = Repl::stdio(interpreter);
+    if let Some(prompt) = prompt {
+        repl = repl.with_prompt(prompt);
+    }
+    if let Some(banner) = banner {
+        repl = repl.with_banner(Some(banner).filter(|banner| !banner.is_empty()));
+    }
+    let _ = repl.run();
+}
+
+/// Applies the last `--lang` before any other argument is checked, so every message, including
+/// errors about arguments that come before it, is in that language.
+fn resolve_lang(args: &[String]) {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lang" => match args.next().map(|lang| lang.parse::<Lang>()) {
+                Some(Ok(lang)) => set_lang(lang),
+                Some(Err(err)) => {
+                    eprintln!("{}", err);
+                    usage();
+                }
+                None => usage(),
+            },
+            "-e" | "--eval" | "--prompt" | "--banner" | "-A" | "-W" | "-D" => {
+                args.next();
+            }
+            "--" => break,
+            _ => {}
+        }
+    }
+}
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    resolve_lang(&args);
+    let mut args = args.into_iter();
+    let mut script = None;
+    let mut eval = None;
+    let mut dump_tokens = false;
+    let mut check = false;
+    let mut watch = false;
+    let mut time = false;
+    let mut script_args = Vec::new();
+    let mut lints = LintLevels::default();
+    let mut error_format = ErrorFormat::default();
+    let mut color = ColorChoice::Auto;
+    let mut prompt = env::var("LOX_PROMPT").ok();
+    let mut banner = env::var("LOX_BANNER").ok();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lang" => {
+                args.next();
+            }
+            arg if arg.starts_with("--color=") => {
+                match arg["--color=".len()..].parse::<ColorChoice>() {
+                    Ok(choice) => color = choice,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        usage();
+                    }
+                }
+            }
+            arg if arg.starts_with("--error-format=") => {
+                match arg["--error-format=".len()..].parse::<ErrorFormat>() {
+                    Ok(format) => error_format = format,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        usage();
+                    }
+                }
+            }
+            "-A" | "-W" | "-D" => {
+                let level = match arg.as_str() {
+                    "-A" => Level::Allow,
+                    "-W" => Level::Warn,
+                    _ => Level::Deny,
+                };
+                let name = args.next().unwrap_or_else(|| usage());
+                if let Err(err) = lints.set(&name, level) {
+                    eprintln!("{}", err);
+                    usage();
+                }
+            }
+            "-e" | "--eval" => match args.next() {
+                Some(source) if eval.is_none() => eval = Some(source),
+                _ => usage(),
+            },
+            "--prompt" => prompt = Some(args.next().unwrap_or_else(|| usage())),
+            "--banner" => banner = Some(args.next().unwrap_or_else(|| usage())),
+            "--no-banner" => banner = Some(String::new()),
+            "--dump-tokens" => dump_tokens = true,
+            "--check" => check = true,
+            "--watch" => watch = true,
+            "--time" => time = true,
+            "--" => script_args.extend(args.by_ref()),
+            _ if script.is_none() => script = Some(arg),
+            _ => usage(),
+        }
+    }
 
-    match args.len() {
-        0 => unreachable!(),
-        1 => Interpreter::run_prompt(),
-        2 => Interpreter::run_file(&args[0]),
-        _ => println!("Usage is: cargo run <path/to/script>"),
+    let mut interpreter = Interpreter::new()
+        .with_dump_tokens(dump_tokens)
+        .with_check(check)
+        .with_time(time)
+        .with_script_args(script_args)
+        .with_lints(lints)
+        .with_error_format(error_format)
+        .with_color(color);
+    match (script, eval) {
+        (Some(_), Some(_)) => usage(),
+        (Some(path), None) if watch => watch_file(&interpreter, Path::new(&path)),
+        (_, _) if watch => usage(),
+        (Some(path), None) => exit_on_error(interpreter.run_file(path)),
+        (None, Some(source)) => exit_on_error(interpreter.run(&source)),
+        // Arguments after `--` are for a script; the REPL has nothing to pass them to.
+        (None, None) if !interpreter.script_args().is_empty() => usage(),
+        (None, None) => run_repl(interpreter, prompt, banner),
     }
 }